@@ -2,6 +2,7 @@ use nu_ansi_term::{Color, Style};
 use nu_protocol::hir::FlatShape;
 use nu_source::{Span, Spanned};
 use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashSet;
 use std::error::Error;
 use std::ops::Deref;
 use std::str::Bytes;
@@ -12,266 +13,674 @@ pub trait Palette {
 }
 
 #[derive(Debug, Clone, Default)]
-pub struct DefaultPalette {}
+pub struct DefaultPalette {
+    theme: Theme,
+}
 
 impl Palette for DefaultPalette {
     fn styles_for_shape(&self, shape: &Spanned<FlatShape>) -> Vec<Spanned<Style>> {
-        match &shape.item {
-            FlatShape::BareMember => single_style_span(Color::Yellow.bold(), shape.span),
-            FlatShape::CloseDelimiter(_) => single_style_span(Color::White.normal(), shape.span),
-            FlatShape::Comment => single_style_span(Color::Green.bold(), shape.span),
-            FlatShape::Decimal => single_style_span(Color::Purple.bold(), shape.span),
-            FlatShape::Dot => single_style_span(Style::new().fg(Color::White), shape.span),
-            FlatShape::DotDot => single_style_span(Color::Yellow.bold(), shape.span),
-            FlatShape::DotDotLeftAngleBracket => {
-                single_style_span(Color::Yellow.bold(), shape.span)
-            }
-            FlatShape::ExternalCommand => single_style_span(Color::Cyan.normal(), shape.span),
-            FlatShape::ExternalWord => single_style_span(Color::Green.bold(), shape.span),
-            FlatShape::Flag => single_style_span(Color::Blue.bold(), shape.span),
-            FlatShape::Garbage => {
-                single_style_span(Style::new().fg(Color::White).on(Color::Red), shape.span)
-            }
-            FlatShape::GlobPattern => single_style_span(Color::Cyan.bold(), shape.span),
-            FlatShape::Identifier => single_style_span(Color::Purple.normal(), shape.span),
-            FlatShape::Int => single_style_span(Color::Purple.bold(), shape.span),
-            FlatShape::InternalCommand => single_style_span(Color::Cyan.bold(), shape.span),
-            FlatShape::ItVariable => single_style_span(Color::Purple.bold(), shape.span),
-            FlatShape::Keyword => single_style_span(Color::Purple.bold(), shape.span),
-            FlatShape::OpenDelimiter(_) => single_style_span(Color::White.normal(), shape.span),
-            FlatShape::Operator => single_style_span(Color::Yellow.normal(), shape.span),
-            FlatShape::Path => single_style_span(Color::Cyan.normal(), shape.span),
-            FlatShape::Pipe => single_style_span(Color::Purple.bold(), shape.span),
-            FlatShape::Separator => single_style_span(Color::White.normal(), shape.span),
-            FlatShape::ShorthandFlag => single_style_span(Color::Blue.bold(), shape.span),
-            FlatShape::Size { number, unit } => vec![
-                Spanned::<Style> {
-                    span: *number,
-                    item: Color::Purple.bold(),
-                },
-                Spanned::<Style> {
-                    span: *unit,
-                    item: Color::Cyan.bold(),
-                },
-            ],
-            FlatShape::String => single_style_span(Color::Green.normal(), shape.span),
-            FlatShape::StringMember => single_style_span(Color::Yellow.bold(), shape.span),
-            FlatShape::Type => single_style_span(Color::Blue.bold(), shape.span),
-            FlatShape::Variable => single_style_span(Color::Purple.normal(), shape.span),
-            FlatShape::Whitespace => single_style_span(Color::White.normal(), shape.span),
-            FlatShape::Word => single_style_span(Color::Green.normal(), shape.span),
-        }
+        self.theme.style_for_shape(shape)
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct ThemedPalette {
-    theme: Theme,
+    themes: ThemeSelection,
+    is_dark: bool,
 }
 
 impl ThemedPalette {
+    /// Loads a theme document, which may be either a single theme or a
+    /// `{"light": ..., "dark": ...}` pair. For a variant pair, the active
+    /// variant is chosen from the background color the terminal reports in
+    /// `COLORFGBG`, falling back to the dark variant when that is unset or
+    /// malformed; use [`ThemedPalette::for_background`] to pick one
+    /// explicitly instead.
     pub fn new<R: io::Read>(reader: &mut R) -> Result<ThemedPalette, ThemeError> {
-        let theme = serde_json::from_reader(reader)?;
-        Ok(ThemedPalette { theme })
+        let document: RawThemeDocument = serde_json::from_reader(reader)?;
+        let themes = document.resolve()?;
+        let is_dark = match &themes {
+            ThemeSelection::Variants { .. } => detect_terminal_is_dark().unwrap_or(true),
+            ThemeSelection::Single(_) => true,
+        };
+        Ok(ThemedPalette { themes, is_dark })
     }
 
     pub fn default() -> ThemedPalette {
-        let theme = Theme::default();
-        ThemedPalette { theme }
+        ThemedPalette {
+            themes: ThemeSelection::Single(Theme::default()),
+            is_dark: true,
+        }
+    }
+
+    /// Returns a copy of this palette with the explicit light or dark
+    /// variant selected, bypassing terminal background detection.
+    pub fn for_background(&self, is_dark: bool) -> ThemedPalette {
+        ThemedPalette {
+            themes: self.themes.clone(),
+            is_dark,
+        }
+    }
+
+    fn active_theme(&self) -> &Theme {
+        self.themes.active(self.is_dark)
+    }
+
+    /// Writes the active theme back out as JSON, so a loaded (and possibly
+    /// partially-defaulted) theme can be dumped to a file for editing.
+    pub fn write_theme<W: io::Write>(&self, writer: W) -> Result<(), ThemeError> {
+        serde_json::to_writer_pretty(writer, self.active_theme())?;
+        Ok(())
     }
 }
 
 impl Palette for ThemedPalette {
     fn styles_for_shape(&self, shape: &Spanned<FlatShape>) -> Vec<Spanned<Style>> {
+        self.active_theme().style_for_shape(shape)
+    }
+}
+
+#[derive(Debug)]
+pub struct ThemeError {
+    kind: ThemeErrorKind,
+}
+
+#[derive(Debug)]
+enum ThemeErrorKind {
+    Parse(serde_json::error::Error),
+    Link(String),
+}
+
+impl ThemeError {
+    fn link(message: String) -> ThemeError {
+        ThemeError {
+            kind: ThemeErrorKind::Link(message),
+        }
+    }
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ThemeErrorKind::Parse(_) => write!(f, "failure to process theme"),
+            ThemeErrorKind::Link(message) => write!(f, "failure to load theme: {}", message),
+        }
+    }
+}
+
+impl Error for ThemeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            ThemeErrorKind::Parse(serde_err) => Some(serde_err),
+            ThemeErrorKind::Link(_) => None,
+        }
+    }
+}
+
+impl From<serde_json::error::Error> for ThemeError {
+    fn from(serde_err: serde_json::error::Error) -> Self {
+        ThemeError {
+            kind: ThemeErrorKind::Parse(serde_err),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct Theme {
+    bare_member: ThemeStyle,
+    close_delimiter: ThemeStyle,
+    comment: ThemeStyle,
+    decimal: ThemeStyle,
+    dot: ThemeStyle,
+    dot_dot: ThemeStyle,
+    dot_dot_left_angle_bracket: ThemeStyle,
+    external_command: ThemeStyle,
+    external_word: ThemeStyle,
+    flag: ThemeStyle,
+    garbage: ThemeStyle,
+    glob_pattern: ThemeStyle,
+    identifier: ThemeStyle,
+    int: ThemeStyle,
+    internal_command: ThemeStyle,
+    it_variable: ThemeStyle,
+    keyword: ThemeStyle,
+    open_delimiter: ThemeStyle,
+    operator: ThemeStyle,
+    path: ThemeStyle,
+    pipe: ThemeStyle,
+    separator: ThemeStyle,
+    shorthand_flag: ThemeStyle,
+    size_number: ThemeStyle,
+    size_unit: ThemeStyle,
+    string: ThemeStyle,
+    string_member: ThemeStyle,
+    r#type: ThemeStyle,
+    variable: ThemeStyle,
+    whitespace: ThemeStyle,
+    word: ThemeStyle,
+}
+
+impl Theme {
+    fn style_for_shape(&self, shape: &Spanned<FlatShape>) -> Vec<Spanned<Style>> {
         match &shape.item {
-            FlatShape::OpenDelimiter(_) => {
-                single_style_span(self.theme.open_delimiter.normal(), shape.span)
-            }
+            FlatShape::BareMember => single_style_span(self.bare_member.to_style(), shape.span),
             FlatShape::CloseDelimiter(_) => {
-                single_style_span(self.theme.close_delimiter.normal(), shape.span)
+                single_style_span(self.close_delimiter.to_style(), shape.span)
             }
-            FlatShape::ItVariable => single_style_span(self.theme.it_variable.bold(), shape.span),
-            FlatShape::Keyword => single_style_span(self.theme.keyword.bold(), shape.span),
-            FlatShape::Variable => single_style_span(self.theme.variable.normal(), shape.span),
-            FlatShape::Identifier => single_style_span(self.theme.identifier.normal(), shape.span),
-            FlatShape::Type => single_style_span(self.theme.r#type.bold(), shape.span),
-            FlatShape::Operator => single_style_span(self.theme.operator.normal(), shape.span),
+            FlatShape::Comment => single_style_span(self.comment.to_style(), shape.span),
+            FlatShape::Decimal => single_style_span(self.decimal.to_style(), shape.span),
+            FlatShape::Dot => single_style_span(self.dot.to_style(), shape.span),
+            FlatShape::DotDot => single_style_span(self.dot_dot.to_style(), shape.span),
             FlatShape::DotDotLeftAngleBracket => {
-                single_style_span(self.theme.dot_dot.bold(), shape.span)
-            }
-            FlatShape::DotDot => single_style_span(self.theme.dot_dot.bold(), shape.span),
-            FlatShape::Dot => single_style_span(Style::new().fg(*self.theme.dot), shape.span),
-            FlatShape::InternalCommand => {
-                single_style_span(self.theme.internal_command.bold(), shape.span)
+                single_style_span(self.dot_dot_left_angle_bracket.to_style(), shape.span)
             }
             FlatShape::ExternalCommand => {
-                single_style_span(self.theme.external_command.normal(), shape.span)
+                single_style_span(self.external_command.to_style(), shape.span)
             }
             FlatShape::ExternalWord => {
-                single_style_span(self.theme.external_word.bold(), shape.span)
+                single_style_span(self.external_word.to_style(), shape.span)
             }
-            FlatShape::BareMember => single_style_span(self.theme.bare_member.bold(), shape.span),
-            FlatShape::StringMember => {
-                single_style_span(self.theme.string_member.bold(), shape.span)
+            FlatShape::Flag => single_style_span(self.flag.to_style(), shape.span),
+            FlatShape::Garbage => single_style_span(self.garbage.to_style(), shape.span),
+            FlatShape::GlobPattern => single_style_span(self.glob_pattern.to_style(), shape.span),
+            FlatShape::Identifier => single_style_span(self.identifier.to_style(), shape.span),
+            FlatShape::Int => single_style_span(self.int.to_style(), shape.span),
+            FlatShape::InternalCommand => {
+                single_style_span(self.internal_command.to_style(), shape.span)
             }
-            FlatShape::String => single_style_span(self.theme.string.normal(), shape.span),
-            FlatShape::Path => single_style_span(self.theme.path.normal(), shape.span),
-            FlatShape::GlobPattern => single_style_span(self.theme.glob_pattern.bold(), shape.span),
-            FlatShape::Word => single_style_span(self.theme.word.normal(), shape.span),
-            FlatShape::Pipe => single_style_span(self.theme.pipe.bold(), shape.span),
-            FlatShape::Flag => single_style_span(self.theme.flag.bold(), shape.span),
+            FlatShape::ItVariable => single_style_span(self.it_variable.to_style(), shape.span),
+            FlatShape::Keyword => single_style_span(self.keyword.to_style(), shape.span),
+            FlatShape::OpenDelimiter(_) => {
+                single_style_span(self.open_delimiter.to_style(), shape.span)
+            }
+            FlatShape::Operator => single_style_span(self.operator.to_style(), shape.span),
+            FlatShape::Path => single_style_span(self.path.to_style(), shape.span),
+            FlatShape::Pipe => single_style_span(self.pipe.to_style(), shape.span),
+            FlatShape::Separator => single_style_span(self.separator.to_style(), shape.span),
             FlatShape::ShorthandFlag => {
-                single_style_span(self.theme.shorthand_flag.bold(), shape.span)
+                single_style_span(self.shorthand_flag.to_style(), shape.span)
             }
-            FlatShape::Int => single_style_span(self.theme.int.bold(), shape.span),
-            FlatShape::Decimal => single_style_span(self.theme.decimal.bold(), shape.span),
-            FlatShape::Whitespace => single_style_span(self.theme.whitespace.normal(), shape.span),
-            FlatShape::Separator => single_style_span(self.theme.separator.normal(), shape.span),
-            FlatShape::Comment => single_style_span(self.theme.comment.bold(), shape.span),
-            FlatShape::Garbage => single_style_span(
-                Style::new().fg(*self.theme.garbage).on(Color::Red),
-                shape.span,
-            ),
             FlatShape::Size { number, unit } => vec![
                 Spanned::<Style> {
                     span: *number,
-                    item: self.theme.size_number.bold(),
+                    item: self.size_number.to_style(),
                 },
                 Spanned::<Style> {
                     span: *unit,
-                    item: self.theme.size_unit.bold(),
+                    item: self.size_unit.to_style(),
                 },
             ],
+            FlatShape::String => single_style_span(self.string.to_style(), shape.span),
+            FlatShape::StringMember => {
+                single_style_span(self.string_member.to_style(), shape.span)
+            }
+            FlatShape::Type => single_style_span(self.r#type.to_style(), shape.span),
+            FlatShape::Variable => single_style_span(self.variable.to_style(), shape.span),
+            FlatShape::Whitespace => single_style_span(self.whitespace.to_style(), shape.span),
+            FlatShape::Word => single_style_span(self.word.to_style(), shape.span),
         }
     }
 }
 
-#[derive(Debug)]
-pub struct ThemeError {
-    serde_err: serde_json::error::Error,
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            bare_member: ThemeStyle::bold(Color::Yellow),
+            close_delimiter: ThemeStyle::plain(Color::White),
+            comment: ThemeStyle::bold(Color::Green),
+            decimal: ThemeStyle::bold(Color::Purple),
+            dot: ThemeStyle::plain(Color::White),
+            dot_dot: ThemeStyle::bold(Color::Yellow),
+            dot_dot_left_angle_bracket: ThemeStyle::bold(Color::Yellow),
+            external_command: ThemeStyle::plain(Color::Cyan),
+            external_word: ThemeStyle::bold(Color::Green),
+            flag: ThemeStyle::bold(Color::Blue),
+            garbage: ThemeStyle::with_bg(Color::White, Color::Red),
+            glob_pattern: ThemeStyle::bold(Color::Cyan),
+            identifier: ThemeStyle::plain(Color::Purple),
+            int: ThemeStyle::bold(Color::Purple),
+            internal_command: ThemeStyle::bold(Color::Cyan),
+            it_variable: ThemeStyle::bold(Color::Purple),
+            keyword: ThemeStyle::bold(Color::Purple),
+            open_delimiter: ThemeStyle::plain(Color::White),
+            operator: ThemeStyle::plain(Color::Yellow),
+            path: ThemeStyle::plain(Color::Cyan),
+            pipe: ThemeStyle::bold(Color::Purple),
+            separator: ThemeStyle::plain(Color::Red),
+            shorthand_flag: ThemeStyle::bold(Color::Blue),
+            size_number: ThemeStyle::bold(Color::Purple),
+            size_unit: ThemeStyle::bold(Color::Cyan),
+            string: ThemeStyle::plain(Color::Green),
+            string_member: ThemeStyle::bold(Color::Yellow),
+            r#type: ThemeStyle::bold(Color::Blue),
+            variable: ThemeStyle::plain(Color::Purple),
+            whitespace: ThemeStyle::plain(Color::White),
+            word: ThemeStyle::plain(Color::Green),
+        }
+    }
 }
 
-impl fmt::Display for ThemeError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "failure to load theme")
+/// The deserialized shape of a theme file, before value-links (`"@key"`)
+/// between entries have been resolved into their final [`ThemeStyle`]s.
+#[derive(Deserialize, Clone, Debug)]
+struct RawTheme {
+    bare_member: ThemeValue,
+    close_delimiter: ThemeValue,
+    comment: ThemeValue,
+    decimal: ThemeValue,
+    dot: ThemeValue,
+    dot_dot: ThemeValue,
+    dot_dot_left_angle_bracket: ThemeValue,
+    external_command: ThemeValue,
+    external_word: ThemeValue,
+    flag: ThemeValue,
+    garbage: ThemeValue,
+    glob_pattern: ThemeValue,
+    identifier: ThemeValue,
+    int: ThemeValue,
+    internal_command: ThemeValue,
+    it_variable: ThemeValue,
+    keyword: ThemeValue,
+    open_delimiter: ThemeValue,
+    operator: ThemeValue,
+    path: ThemeValue,
+    pipe: ThemeValue,
+    separator: ThemeValue,
+    shorthand_flag: ThemeValue,
+    size_number: ThemeValue,
+    size_unit: ThemeValue,
+    string: ThemeValue,
+    string_member: ThemeValue,
+    r#type: ThemeValue,
+    variable: ThemeValue,
+    whitespace: ThemeValue,
+    word: ThemeValue,
+}
+
+impl RawTheme {
+    /// Looks up a raw entry by its theme-file key name, for following
+    /// `ThemeValue::Link` targets.
+    fn get(&self, key: &str) -> Option<&ThemeValue> {
+        match key {
+            "bare_member" => Some(&self.bare_member),
+            "close_delimiter" => Some(&self.close_delimiter),
+            "comment" => Some(&self.comment),
+            "decimal" => Some(&self.decimal),
+            "dot" => Some(&self.dot),
+            "dot_dot" => Some(&self.dot_dot),
+            "dot_dot_left_angle_bracket" => Some(&self.dot_dot_left_angle_bracket),
+            "external_command" => Some(&self.external_command),
+            "external_word" => Some(&self.external_word),
+            "flag" => Some(&self.flag),
+            "garbage" => Some(&self.garbage),
+            "glob_pattern" => Some(&self.glob_pattern),
+            "identifier" => Some(&self.identifier),
+            "int" => Some(&self.int),
+            "internal_command" => Some(&self.internal_command),
+            "it_variable" => Some(&self.it_variable),
+            "keyword" => Some(&self.keyword),
+            "open_delimiter" => Some(&self.open_delimiter),
+            "operator" => Some(&self.operator),
+            "path" => Some(&self.path),
+            "pipe" => Some(&self.pipe),
+            "separator" => Some(&self.separator),
+            "shorthand_flag" => Some(&self.shorthand_flag),
+            "size_number" => Some(&self.size_number),
+            "size_unit" => Some(&self.size_unit),
+            "string" => Some(&self.string),
+            "string_member" => Some(&self.string_member),
+            "type" => Some(&self.r#type),
+            "variable" => Some(&self.variable),
+            "whitespace" => Some(&self.whitespace),
+            "word" => Some(&self.word),
+            _ => None,
+        }
+    }
+
+    /// Follows `Link(key)` chains starting at `key` until a `Value` is
+    /// reached, erroring on dangling keys or cycles.
+    fn unlink(&self, key: &str) -> Result<ThemeStyle, ThemeError> {
+        let mut current = key;
+        let mut visited = HashSet::new();
+        loop {
+            if !visited.insert(current) {
+                return Err(ThemeError::link(format!(
+                    "cycle detected resolving theme key `{}`",
+                    key
+                )));
+            }
+            match self.get(current) {
+                Some(ThemeValue::Value(style)) => return Ok(style.clone()),
+                Some(ThemeValue::Link(target)) => current = target,
+                None => {
+                    return Err(ThemeError::link(format!(
+                        "theme key `{}` links to unknown key `{}`",
+                        key, current
+                    )))
+                }
+            }
+        }
+    }
+
+    /// Resolves every entry's value-links into a fully-formed [`Theme`].
+    fn resolve(&self) -> Result<Theme, ThemeError> {
+        Ok(Theme {
+            bare_member: self.unlink("bare_member")?,
+            close_delimiter: self.unlink("close_delimiter")?,
+            comment: self.unlink("comment")?,
+            decimal: self.unlink("decimal")?,
+            dot: self.unlink("dot")?,
+            dot_dot: self.unlink("dot_dot")?,
+            dot_dot_left_angle_bracket: self.unlink("dot_dot_left_angle_bracket")?,
+            external_command: self.unlink("external_command")?,
+            external_word: self.unlink("external_word")?,
+            flag: self.unlink("flag")?,
+            garbage: self.unlink("garbage")?,
+            glob_pattern: self.unlink("glob_pattern")?,
+            identifier: self.unlink("identifier")?,
+            int: self.unlink("int")?,
+            internal_command: self.unlink("internal_command")?,
+            it_variable: self.unlink("it_variable")?,
+            keyword: self.unlink("keyword")?,
+            open_delimiter: self.unlink("open_delimiter")?,
+            operator: self.unlink("operator")?,
+            path: self.unlink("path")?,
+            pipe: self.unlink("pipe")?,
+            separator: self.unlink("separator")?,
+            shorthand_flag: self.unlink("shorthand_flag")?,
+            size_number: self.unlink("size_number")?,
+            size_unit: self.unlink("size_unit")?,
+            string: self.unlink("string")?,
+            string_member: self.unlink("string_member")?,
+            r#type: self.unlink("type")?,
+            variable: self.unlink("variable")?,
+            whitespace: self.unlink("whitespace")?,
+            word: self.unlink("word")?,
+        })
     }
 }
 
-impl Error for ThemeError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        Some(&self.serde_err)
+/// The top-level shape of a theme file: either a single theme, applied
+/// regardless of the terminal's background, or a `light`/`dark` pair to
+/// choose between.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+enum RawThemeDocument {
+    Variants { light: RawTheme, dark: RawTheme },
+    Single(RawTheme),
+}
+
+impl RawThemeDocument {
+    fn resolve(&self) -> Result<ThemeSelection, ThemeError> {
+        match self {
+            RawThemeDocument::Variants { light, dark } => Ok(ThemeSelection::Variants {
+                light: light.resolve()?,
+                dark: dark.resolve()?,
+            }),
+            RawThemeDocument::Single(theme) => Ok(ThemeSelection::Single(theme.resolve()?)),
+        }
     }
 }
 
-impl From<serde_json::error::Error> for ThemeError {
-    fn from(serde_err: serde_json::error::Error) -> Self {
-        ThemeError { serde_err }
+/// A fully-resolved theme document: either one [`Theme`] used regardless of
+/// background, or a `light`/`dark` pair to choose between at render time.
+#[derive(Debug, Clone)]
+enum ThemeSelection {
+    Single(Theme),
+    Variants { light: Theme, dark: Theme },
+}
+
+impl ThemeSelection {
+    fn active(&self, is_dark: bool) -> &Theme {
+        match self {
+            ThemeSelection::Single(theme) => theme,
+            ThemeSelection::Variants { light, dark } => {
+                if is_dark {
+                    dark
+                } else {
+                    light
+                }
+            }
+        }
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-struct Theme {
-    bare_member: ThemeColor,
-    close_delimiter: ThemeColor,
-    comment: ThemeColor,
-    decimal: ThemeColor,
-    dot: ThemeColor,
-    dot_dot: ThemeColor,
-    dot_dot_left_angle_bracket: ThemeColor,
-    external_command: ThemeColor,
-    external_word: ThemeColor,
-    flag: ThemeColor,
-    garbage: ThemeColor,
-    glob_pattern: ThemeColor,
-    identifier: ThemeColor,
-    int: ThemeColor,
-    internal_command: ThemeColor,
-    it_variable: ThemeColor,
-    keyword: ThemeColor,
-    open_delimiter: ThemeColor,
-    operator: ThemeColor,
-    path: ThemeColor,
-    pipe: ThemeColor,
-    separator: ThemeColor,
-    shorthand_flag: ThemeColor,
-    size_number: ThemeColor,
-    size_unit: ThemeColor,
-    string: ThemeColor,
-    string_member: ThemeColor,
-    r#type: ThemeColor,
-    variable: ThemeColor,
-    whitespace: ThemeColor,
-    word: ThemeColor,
+/// Best-effort detection of whether the terminal's background is dark, via
+/// the `COLORFGBG` environment variable that many terminal emulators (rxvt,
+/// konsole, the Windows Terminal, ...) already export as `"fg;bg"` ANSI
+/// color indices. Returns `None` when the variable is unset or malformed,
+/// since nushell has no portable, input-safe way to query an arbitrary
+/// terminal directly without risking consuming the user's real input.
+fn detect_terminal_is_dark() -> Option<bool> {
+    let value = std::env::var("COLORFGBG").ok()?;
+    let bg_index: u8 = value.rsplit(';').next()?.trim().parse().ok()?;
+
+    // The 16-color ANSI palette's upper half (8-15) is the "bright" set;
+    // terminals overwhelmingly use one of the low, dim indices for a dark
+    // background and a bright one (7 or 8-15) for a light background.
+    Some(!matches!(bg_index, 7..=15))
 }
 
-impl Default for Theme {
-    fn default() -> Self {
-        Theme {
-            bare_member: ThemeColor(Color::Yellow),
-            close_delimiter: ThemeColor(Color::White),
-            comment: ThemeColor(Color::Green),
-            decimal: ThemeColor(Color::Purple),
-            dot: ThemeColor(Color::White),
-            dot_dot: ThemeColor(Color::Yellow),
-            dot_dot_left_angle_bracket: ThemeColor(Color::Yellow),
-            external_command: ThemeColor(Color::Cyan),
-            external_word: ThemeColor(Color::Green),
-            flag: ThemeColor(Color::Blue),
-            garbage: ThemeColor(Color::White),
-            glob_pattern: ThemeColor(Color::Cyan),
-            identifier: ThemeColor(Color::Purple),
-            int: ThemeColor(Color::Purple),
-            internal_command: ThemeColor(Color::Cyan),
-            it_variable: ThemeColor(Color::Purple),
-            keyword: ThemeColor(Color::Purple),
-            open_delimiter: ThemeColor(Color::White),
-            operator: ThemeColor(Color::Yellow),
-            path: ThemeColor(Color::Cyan),
-            pipe: ThemeColor(Color::Purple),
-            separator: ThemeColor(Color::Red),
-            shorthand_flag: ThemeColor(Color::Blue),
-            size_number: ThemeColor(Color::Purple),
-            size_unit: ThemeColor(Color::Cyan),
-            string: ThemeColor(Color::Green),
-            string_member: ThemeColor(Color::Yellow),
-            r#type: ThemeColor(Color::Blue),
-            variable: ThemeColor(Color::Purple),
-            whitespace: ThemeColor(Color::White),
-            word: ThemeColor(Color::Green),
-            // These should really be Styles and not colors
-            // leave this here for the next change to make
-            // ThemeColor, ThemeStyle.
-            // open_delimiter: ThemeColor(Color::White.normal()),
-            // close_delimiter: ThemeColor(Color::White.normal()),
-            // it_variable: ThemeColor(Color::Purple.bold()),
-            // variable: ThemeColor(Color::Purple.normal()),
-            // r#type: ThemeColor(Color::Blue.bold()),
-            // identifier: ThemeColor(Color::Purple.normal()),
-            // operator: ThemeColor(Color::Yellow.normal()),
-            // dot: ThemeColor(Color::White),
-            // dot_dot: ThemeColor(Color::Yellow.bold()),
-            // //missing DotDotLeftAngleBracket
-            // internal_command: ThemeColor(Color::Cyan.bold()),
-            // external_command: ThemeColor(Color::Cyan.normal()),
-            // external_word: ThemeColor(Color::Green.bold()),
-            // bare_member: ThemeColor(Color::Yellow.bold()),
-            // string: ThemeColor(Color::Green.normal()),
-            // string_member: ThemeColor(Color::Yellow.bold()),
-            // path: ThemeColor(Color::Cyan.normal()),
-            // glob_pattern: ThemeColor(Color::Cyan.bold()),
-            // word: ThemeColor(Color::Green.normal()),
-            // keyword: ThemeColor(Color::Purple.bold()),
-            // pipe: ThemeColor(Color::Purple.bold()),
-            // flag: ThemeColor(Color::Blue.bold()),
-            // shorthand_flag: ThemeColor(Color::Blue.bold()),
-            // int: ThemeColor(Color::Purple.bold()),
-            // decimal: ThemeColor(Color::Purple.bold()),
-            // garbage: ThemeColor(Style::new().fg(Color::White).on(Color::Red)),
-            // whitespace: ThemeColor(Color::White.normal()),
-            // separator: ThemeColor(Color::Red),
-            // comment: ThemeColor(Color::Green.bold()),
-            // size_number: ThemeColor(Color::Purple.bold()),
-            // size_unit: ThemeColor(Color::Cyan.bold()),
+/// A single theme entry as written in a theme file: either a resolved style
+/// or a `"@other_key"` link to another entry in the same theme.
+#[derive(Debug, Clone)]
+enum ThemeValue {
+    Value(ThemeStyle),
+    Link(String),
+}
+
+impl<'de> Deserialize<'de> for ThemeValue {
+    fn deserialize<D>(deserializer: D) -> Result<ThemeValue, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match ThemeStyleRepr::deserialize(deserializer)? {
+            ThemeStyleRepr::Shorthand(s) => match s.strip_prefix('@') {
+                Some(key) => Ok(ThemeValue::Link(key.to_string())),
+                None => Ok(ThemeValue::Value(
+                    ThemeStyleRepr::Shorthand(s).into_theme_style()?,
+                )),
+            },
+            full => Ok(ThemeValue::Value(full.into_theme_style()?)),
+        }
+    }
+}
+
+/// A single modifier that can be layered onto a [`ThemeStyle`]'s foreground
+/// color, mirroring the boolean flags on [`nu_ansi_term::Style`]. `Style`
+/// only exposes a single `is_blink` flag (via `.blink()`), so `SlowBlink`
+/// and `RapidBlink` are both accepted as theme-file modifier names but map
+/// onto the same underlying attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Modifier {
+    Bold,
+    Dim,
+    Italic,
+    Underlined,
+    SlowBlink,
+    RapidBlink,
+    Reversed,
+    Hidden,
+    CrossedOut,
+}
+
+impl Modifier {
+    fn from_str<E>(s: &str) -> Result<Modifier, E>
+    where
+        E: serde::de::Error,
+    {
+        match s {
+            "bold" => Ok(Modifier::Bold),
+            "dim" => Ok(Modifier::Dim),
+            "italic" => Ok(Modifier::Italic),
+            "underlined" => Ok(Modifier::Underlined),
+            "slow_blink" => Ok(Modifier::SlowBlink),
+            "rapid_blink" => Ok(Modifier::RapidBlink),
+            "reversed" => Ok(Modifier::Reversed),
+            "hidden" => Ok(Modifier::Hidden),
+            "crossed_out" => Ok(Modifier::CrossedOut),
+            _ => Err(E::custom(format!(
+                "invalid modifier `{}`, expected one of: bold, dim, italic, underlined, \
+                 slow_blink, rapid_blink, reversed, hidden, crossed_out",
+                s
+            ))),
+        }
+    }
+
+    fn apply(self, style: Style) -> Style {
+        match self {
+            Modifier::Bold => style.bold(),
+            Modifier::Dim => style.dimmed(),
+            Modifier::Italic => style.italic(),
+            Modifier::Underlined => style.underline(),
+            Modifier::SlowBlink | Modifier::RapidBlink => style.blink(),
+            Modifier::Reversed => style.reverse(),
+            Modifier::Hidden => style.hidden(),
+            Modifier::CrossedOut => style.strikethrough(),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Modifier::Bold => "bold",
+            Modifier::Dim => "dim",
+            Modifier::Italic => "italic",
+            Modifier::Underlined => "underlined",
+            Modifier::SlowBlink => "slow_blink",
+            Modifier::RapidBlink => "rapid_blink",
+            Modifier::Reversed => "reversed",
+            Modifier::Hidden => "hidden",
+            Modifier::CrossedOut => "crossed_out",
+        }
+    }
+}
+
+impl Serialize for Modifier {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Modifier {
+    fn deserialize<D>(deserializer: D) -> Result<Modifier, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Modifier::from_str(&s)
+    }
+}
+
+/// A theme entry: a foreground color, an optional background color, and the
+/// modifiers (bold, italic, ...) that decorate it. Deserializes either from
+/// a full `{fg, bg, modifiers}` map or, for backward compatibility with
+/// older theme files, from a bare hex string that is equivalent to
+/// `{fg: <string>, modifiers: []}` with no background.
+#[derive(Debug, Clone, Default)]
+struct ThemeStyle {
+    fg: ThemeColor,
+    bg: Option<ThemeColor>,
+    modifiers: Vec<Modifier>,
+}
+
+impl ThemeStyle {
+    fn plain(color: Color) -> ThemeStyle {
+        ThemeStyle {
+            fg: ThemeColor(color),
+            bg: None,
+            modifiers: Vec::new(),
+        }
+    }
+
+    fn bold(color: Color) -> ThemeStyle {
+        ThemeStyle {
+            fg: ThemeColor(color),
+            bg: None,
+            modifiers: vec![Modifier::Bold],
+        }
+    }
+
+    fn with_bg(fg: Color, bg: Color) -> ThemeStyle {
+        ThemeStyle {
+            fg: ThemeColor(fg),
+            bg: Some(ThemeColor(bg)),
+            modifiers: Vec::new(),
+        }
+    }
+
+    fn to_style(&self) -> Style {
+        let style = self
+            .modifiers
+            .iter()
+            .fold(Style::new().fg(*self.fg), |style, modifier| {
+                modifier.apply(style)
+            });
+        match &self.bg {
+            Some(bg) => style.on(**bg),
+            None => style,
+        }
+    }
+}
+
+impl Serialize for ThemeStyle {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("ThemeStyle", 3)?;
+        state.serialize_field("fg", &self.fg)?;
+        state.serialize_field("bg", &self.bg)?;
+        state.serialize_field("modifiers", &self.modifiers)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for ThemeStyle {
+    fn deserialize<D>(deserializer: D) -> Result<ThemeStyle, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        ThemeStyleRepr::deserialize(deserializer)?.into_theme_style()
+    }
+}
+
+/// The on-the-wire shape of a [`ThemeStyle`]: either a full
+/// `{fg, bg, modifiers}` map, or, for backward compatibility with older
+/// theme files, a bare hex string equivalent to `{fg: <string>, modifiers: []}`
+/// with no background. Shared by `ThemeStyle`'s own `Deserialize` impl and
+/// [`ThemeValue`]'s (which also needs to see the raw shorthand string, to
+/// recognize a `"@other_key"` link, before committing to a color), so the
+/// two don't drift apart.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ThemeStyleRepr {
+    Shorthand(String),
+    Full {
+        fg: ThemeColor,
+        #[serde(default)]
+        bg: Option<ThemeColor>,
+        #[serde(default)]
+        modifiers: Vec<Modifier>,
+    },
+}
+
+impl ThemeStyleRepr {
+    fn into_theme_style<E>(self) -> Result<ThemeStyle, E>
+    where
+        E: serde::de::Error,
+    {
+        match self {
+            ThemeStyleRepr::Shorthand(s) => Ok(ThemeStyle {
+                fg: ThemeColor::from_str(&s)?,
+                bg: None,
+                modifiers: Vec::new(),
+            }),
+            ThemeStyleRepr::Full { fg, bg, modifiers } => Ok(ThemeStyle { fg, bg, modifiers }),
         }
     }
 }
@@ -292,7 +701,7 @@ impl Serialize for ThemeColor {
     where
         S: Serializer,
     {
-        serializer.serialize_str("TODO: IMPLEMENT SERIALIZATION")
+        serializer.serialize_str(&self.to_theme_string())
     }
 }
 
@@ -307,15 +716,201 @@ impl<'de> Deserialize<'de> for ThemeColor {
 }
 
 impl ThemeColor {
+    /// Renders this color back to the grammar [`ThemeColor::from_str`]
+    /// accepts: a name for one of the 16 standard colors, a decimal ANSI
+    /// index for `Fixed`, and a canonical `#RRGGBB` hex string only for a
+    /// genuine `Rgb` value. This preserves the original `Color` variant
+    /// across a serialize/deserialize round trip -- approximating every
+    /// color to RGB here would turn a named or `Fixed` color into a
+    /// different `Rgb` variant, which `Color`'s structural `PartialEq`
+    /// would then see as unequal to the original.
+    fn to_theme_string(&self) -> String {
+        if let Some(name) = ThemeColor::color_name(self.0) {
+            return name.to_string();
+        }
+        if let Color::Fixed(index) = self.0 {
+            return index.to_string();
+        }
+        self.to_hex()
+    }
+
+    /// The inverse of [`ThemeColor::named_color`]: the canonical name this
+    /// serializes back out as, for the colors that one accepts by name.
+    fn color_name(color: Color) -> Option<&'static str> {
+        match color {
+            Color::Black => Some("black"),
+            Color::DarkGray => Some("dark_gray"),
+            Color::LightGray => Some("light_gray"),
+            Color::Red => Some("red"),
+            Color::LightRed => Some("light_red"),
+            Color::Green => Some("green"),
+            Color::LightGreen => Some("light_green"),
+            Color::Yellow => Some("yellow"),
+            Color::LightYellow => Some("light_yellow"),
+            Color::Blue => Some("blue"),
+            Color::LightBlue => Some("light_blue"),
+            Color::Purple => Some("purple"),
+            Color::LightPurple => Some("light_purple"),
+            Color::Magenta => Some("magenta"),
+            Color::LightMagenta => Some("light_magenta"),
+            Color::Cyan => Some("cyan"),
+            Color::LightCyan => Some("light_cyan"),
+            Color::White => Some("white"),
+            _ => None,
+        }
+    }
+
+    /// Approximates this color as a canonical `#RRGGBB` hex string,
+    /// resolving named and indexed colors to their approximate RGB value.
+    /// Only used as the [`ThemeColor::to_theme_string`] fallback for `Rgb`
+    /// colors, which have no shorter grammar form.
+    fn to_hex(&self) -> String {
+        let (r, g, b) = ThemeColor::to_rgb(self.0);
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+
+    fn to_rgb(color: Color) -> (u8, u8, u8) {
+        match color {
+            Color::Rgb(r, g, b) => (r, g, b),
+            Color::Fixed(index) => ThemeColor::fixed_to_rgb(index),
+            Color::Black => (0, 0, 0),
+            Color::DarkGray => (128, 128, 128),
+            Color::Red => (128, 0, 0),
+            Color::LightRed => (255, 0, 0),
+            Color::Green => (0, 128, 0),
+            Color::LightGreen => (0, 255, 0),
+            Color::Yellow => (128, 128, 0),
+            Color::LightYellow => (255, 255, 0),
+            Color::Blue => (0, 0, 128),
+            Color::LightBlue => (0, 0, 255),
+            Color::Purple => (128, 0, 128),
+            Color::LightPurple => (255, 0, 255),
+            Color::Magenta => (128, 0, 128),
+            Color::LightMagenta => (255, 0, 255),
+            Color::Cyan => (0, 128, 128),
+            Color::LightCyan => (0, 255, 255),
+            Color::White => (192, 192, 192),
+            Color::LightGray => (255, 255, 255),
+            _ => (255, 255, 255),
+        }
+    }
+
+    /// Approximates an xterm-256 palette index as RGB: the 16 standard
+    /// colors, the 6x6x6 color cube (16-231), then the grayscale ramp.
+    fn fixed_to_rgb(index: u8) -> (u8, u8, u8) {
+        match index {
+            0..=15 => ThemeColor::to_rgb(ThemeColor::ansi16(index)),
+            16..=231 => {
+                let i = index - 16;
+                let level = |c: u8| if c == 0 { 0 } else { 55 + 40 * c };
+                (level(i / 36), level((i / 6) % 6), level(i % 6))
+            }
+            _ => {
+                let level = 8 + 10 * (index - 232);
+                (level, level, level)
+            }
+        }
+    }
+
+    fn ansi16(index: u8) -> Color {
+        match index {
+            0 => Color::Black,
+            1 => Color::Red,
+            2 => Color::Green,
+            3 => Color::Yellow,
+            4 => Color::Blue,
+            5 => Color::Purple,
+            6 => Color::Cyan,
+            7 => Color::White,
+            8 => Color::DarkGray,
+            9 => Color::LightRed,
+            10 => Color::LightGreen,
+            11 => Color::LightYellow,
+            12 => Color::LightBlue,
+            13 => Color::LightPurple,
+            14 => Color::LightCyan,
+            _ => Color::LightGray,
+        }
+    }
+
     fn from_str<E>(s: &str) -> Result<ThemeColor, E>
     where
         E: serde::de::Error,
     {
-        let mut bytes = s.bytes();
-        let r = ThemeColor::xtoi(&mut bytes)?;
-        let g = ThemeColor::xtoi(&mut bytes)?;
-        let b = ThemeColor::xtoi(&mut bytes)?;
-        Ok(ThemeColor(Color::Rgb(r, g, b)))
+        let stripped = s.strip_prefix('#').unwrap_or(s);
+
+        if let Some(color) = ThemeColor::named_color(stripped) {
+            return Ok(ThemeColor(color));
+        }
+
+        if stripped.len() <= 3 && stripped.bytes().all(|b| b.is_ascii_digit()) {
+            return stripped
+                .parse::<u8>()
+                .map(|index| ThemeColor(Color::Fixed(index)))
+                .map_err(|_| ThemeColor::grammar_error(s));
+        }
+
+        // xtoi fails on malformed hex digits with its own low-level message;
+        // surface the one grammar error callers actually want to see instead.
+        let xtoi = |bytes: &mut Bytes| -> Result<u8, E> {
+            ThemeColor::xtoi(bytes).map_err(|_: E| ThemeColor::grammar_error(s))
+        };
+
+        match stripped.len() {
+            // RRGGBB
+            6 => {
+                let mut bytes = stripped.bytes();
+                let r = xtoi(&mut bytes)?;
+                let g = xtoi(&mut bytes)?;
+                let b = xtoi(&mut bytes)?;
+                Ok(ThemeColor(Color::Rgb(r, g, b)))
+            }
+            // RRGGBBAA - the alpha channel is parsed for compatibility with
+            // exported palettes but discarded, since terminals have no alpha
+            8 => {
+                let mut bytes = stripped.bytes();
+                let r = xtoi(&mut bytes)?;
+                let g = xtoi(&mut bytes)?;
+                let b = xtoi(&mut bytes)?;
+                let _a = xtoi(&mut bytes)?;
+                Ok(ThemeColor(Color::Rgb(r, g, b)))
+            }
+            _ => Err(ThemeColor::grammar_error(s)),
+        }
+    }
+
+    fn grammar_error<E>(s: &str) -> E
+    where
+        E: serde::de::Error,
+    {
+        E::custom(format!(
+            "invalid color `{}`, expected one of: #RRGGBB[AA] | name | ansi-index",
+            s
+        ))
+    }
+
+    fn named_color(name: &str) -> Option<Color> {
+        match name {
+            "black" => Some(Color::Black),
+            "dark_gray" | "dark_grey" => Some(Color::DarkGray),
+            "light_gray" | "light_grey" => Some(Color::LightGray),
+            "red" | "dark_red" => Some(Color::Red),
+            "light_red" => Some(Color::LightRed),
+            "green" | "dark_green" => Some(Color::Green),
+            "light_green" => Some(Color::LightGreen),
+            "yellow" | "dark_yellow" => Some(Color::Yellow),
+            "light_yellow" => Some(Color::LightYellow),
+            "blue" | "dark_blue" => Some(Color::Blue),
+            "light_blue" => Some(Color::LightBlue),
+            "purple" | "dark_purple" => Some(Color::Purple),
+            "light_purple" => Some(Color::LightPurple),
+            "magenta" | "dark_magenta" => Some(Color::Magenta),
+            "light_magenta" => Some(Color::LightMagenta),
+            "cyan" | "dark_cyan" => Some(Color::Cyan),
+            "light_cyan" => Some(Color::LightCyan),
+            "white" => Some(Color::White),
+            _ => None,
+        }
     }
 
     fn xtoi<E>(b: &mut Bytes) -> Result<u8, E>
@@ -339,7 +934,8 @@ impl ThemeColor {
     {
         match character {
             b'0'..=b'9' => Ok(character - b'0'),
-            b'a'..=b'z' => Ok(character - (b'a' - 10)),
+            b'a'..=b'f' => Ok(character - (b'a' - 10)),
+            b'A'..=b'F' => Ok(character - (b'A' - 10)),
             _ => Err(E::custom(format!("invalid character {}", character))),
         }
     }
@@ -351,8 +947,8 @@ fn single_style_span(style: Style, span: Span) -> Vec<Spanned<Style>> {
 
 #[cfg(test)]
 mod tests {
-    use super::{Palette, ThemedPalette};
-    use nu_ansi_term::Color;
+    use super::{Palette, ThemeColor, ThemedPalette};
+    use nu_ansi_term::{Color, Style};
     use nu_protocol::hir::FlatShape;
     use nu_source::{Span, Spanned};
     use std::io::Cursor;
@@ -388,7 +984,7 @@ mod tests {
     "size_unit": "a359cc",
     "string": "a359cc",
     "string_member": "a359cc",
-    "type": "a359cc",
+    "type": {"fg": "a359cc", "modifiers": ["bold", "italic"]},
     "variable": "a359cc",
     "whitespace": "a359cc",
     "word": "a359cc"
@@ -404,9 +1000,276 @@ mod tests {
         assert_eq!(
             styled[0],
             Spanned {
-                item: Color::Rgb(163, 89, 204).bold(),
+                item: Color::Rgb(163, 89, 204).bold().italic(),
                 span: Span::new(4, 9),
             },
         );
     }
+
+    #[test]
+    fn every_modifier_applies_through_to_style() {
+        use super::{Modifier, ThemeStyle};
+
+        let base = Style::new().fg(Color::White);
+        let cases = [
+            (Modifier::Bold, base.bold()),
+            (Modifier::Dim, base.dimmed()),
+            (Modifier::Italic, base.italic()),
+            (Modifier::Underlined, base.underline()),
+            (Modifier::SlowBlink, base.blink()),
+            (Modifier::RapidBlink, base.blink()),
+            (Modifier::Reversed, base.reverse()),
+            (Modifier::Hidden, base.hidden()),
+            (Modifier::CrossedOut, base.strikethrough()),
+        ];
+
+        for (modifier, expected) in cases {
+            let theme_style = ThemeStyle {
+                fg: ThemeColor(Color::White),
+                bg: None,
+                modifiers: vec![modifier],
+            };
+            assert_eq!(theme_style.to_style(), expected, "{:?}", modifier);
+        }
+
+        // SlowBlink and RapidBlink both set the single `is_blink` flag `Style`
+        // actually exposes, so they must produce identical styles.
+        assert_eq!(
+            ThemeStyle {
+                fg: ThemeColor(Color::White),
+                bg: None,
+                modifiers: vec![Modifier::SlowBlink],
+            }
+            .to_style(),
+            ThemeStyle {
+                fg: ThemeColor(Color::White),
+                bg: None,
+                modifiers: vec![Modifier::RapidBlink],
+            }
+            .to_style(),
+        );
+    }
+
+    #[test]
+    fn theme_color_parses_hex_with_and_without_hash() {
+        assert_eq!(
+            *ThemeColor::from_str::<serde_json::Error>("a359cc").unwrap(),
+            Color::Rgb(163, 89, 204),
+        );
+        assert_eq!(
+            *ThemeColor::from_str::<serde_json::Error>("#a359cc").unwrap(),
+            Color::Rgb(163, 89, 204),
+        );
+    }
+
+    #[test]
+    fn theme_color_parses_hex_with_alpha() {
+        assert_eq!(
+            *ThemeColor::from_str::<serde_json::Error>("#a359ccff").unwrap(),
+            Color::Rgb(163, 89, 204),
+        );
+    }
+
+    #[test]
+    fn theme_color_parses_ansi_index() {
+        assert_eq!(
+            *ThemeColor::from_str::<serde_json::Error>("12").unwrap(),
+            Color::Fixed(12),
+        );
+    }
+
+    #[test]
+    fn theme_color_parses_named_color() {
+        assert_eq!(
+            *ThemeColor::from_str::<serde_json::Error>("dark_cyan").unwrap(),
+            Color::Cyan,
+        );
+        assert_eq!(
+            *ThemeColor::from_str::<serde_json::Error>("purple").unwrap(),
+            Color::Purple,
+        );
+    }
+
+    #[test]
+    fn theme_color_round_trips_variant_through_theme_string() {
+        for (color, expected) in [
+            (Color::Purple, "purple"),
+            (Color::Fixed(12), "12"),
+            (Color::Rgb(163, 89, 204), "#a359cc"),
+        ] {
+            let theme_color = ThemeColor(color);
+            assert_eq!(theme_color.to_theme_string(), expected);
+            assert_eq!(
+                *ThemeColor::from_str::<serde_json::Error>(&theme_color.to_theme_string()).unwrap(),
+                color,
+            );
+        }
+    }
+
+    #[test]
+    fn theme_color_rejects_garbage() {
+        assert!(ThemeColor::from_str::<serde_json::Error>("not-a-color").is_err());
+    }
+
+    #[test]
+    fn theme_color_rejects_non_hex_digits_with_grammar_error() {
+        let err = ThemeColor::from_str::<serde_json::Error>("xyz123").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid color `xyz123`, expected one of: #RRGGBB[AA] | name | ansi-index",
+        );
+    }
+
+    /// A full, valid theme JSON document with every key set to `"a359cc"`,
+    /// so tests only need to override the one or two keys they care about.
+    fn full_theme_json(overrides: &[(&str, serde_json::Value)]) -> String {
+        let mut value = serde_json::json!({
+            "bare_member": "a359cc",
+            "close_delimiter": "a359cc",
+            "comment": "a359cc",
+            "decimal": "a359cc",
+            "dot": "a359cc",
+            "dot_dot": "a359cc",
+            "dot_dot_left_angle_bracket": "a359cc",
+            "external_command": "a359cc",
+            "external_word": "a359cc",
+            "flag": "a359cc",
+            "garbage": "a359cc",
+            "glob_pattern": "a359cc",
+            "identifier": "a359cc",
+            "int": "a359cc",
+            "internal_command": "a359cc",
+            "it_variable": "a359cc",
+            "keyword": "a359cc",
+            "open_delimiter": "a359cc",
+            "operator": "a359cc",
+            "path": "a359cc",
+            "pipe": "a359cc",
+            "separator": "a359cc",
+            "shorthand_flag": "a359cc",
+            "size_number": "a359cc",
+            "size_unit": "a359cc",
+            "string": "a359cc",
+            "string_member": "a359cc",
+            "type": "a359cc",
+            "variable": "a359cc",
+            "whitespace": "a359cc",
+            "word": "a359cc"
+        });
+        for (key, v) in overrides {
+            value[key] = v.clone();
+        }
+        value.to_string()
+    }
+
+    #[test]
+    fn linked_theme_key_resolves_to_target_style() {
+        let json = full_theme_json(&[
+            ("decimal", serde_json::json!("ff0000")),
+            ("int", serde_json::json!("@decimal")),
+        ]);
+        let mut json_reader = Cursor::new(json);
+        let themed_palette = ThemedPalette::new(&mut json_reader).unwrap();
+        let test_shape = Spanned {
+            item: FlatShape::Int,
+            span: Span::new(0, 1),
+        };
+        let styled = themed_palette.styles_for_shape(&test_shape);
+        assert_eq!(styled[0].item, Color::Rgb(255, 0, 0).normal());
+    }
+
+    #[test]
+    fn linked_theme_cycle_is_rejected() {
+        let json = full_theme_json(&[
+            ("decimal", serde_json::json!("@int")),
+            ("int", serde_json::json!("@decimal")),
+        ]);
+        let mut json_reader = Cursor::new(json);
+        assert!(ThemedPalette::new(&mut json_reader).is_err());
+    }
+
+    #[test]
+    fn linked_theme_dangling_key_is_rejected() {
+        let json = full_theme_json(&[("decimal", serde_json::json!("@does_not_exist"))]);
+        let mut json_reader = Cursor::new(json);
+        assert!(ThemedPalette::new(&mut json_reader).is_err());
+    }
+
+    #[test]
+    fn theme_round_trips_through_serialization() {
+        let original = ThemedPalette::default();
+        let mut buffer = Vec::new();
+        original.write_theme(&mut buffer).unwrap();
+
+        let mut reader = Cursor::new(buffer);
+        let round_tripped = ThemedPalette::new(&mut reader).unwrap();
+
+        let test_shape = Spanned {
+            item: FlatShape::Keyword,
+            span: Span::new(0, 1),
+        };
+        assert_eq!(
+            original.styles_for_shape(&test_shape),
+            round_tripped.styles_for_shape(&test_shape),
+        );
+    }
+
+    #[test]
+    fn default_garbage_style_has_red_background() {
+        let palette = ThemedPalette::default();
+        let test_shape = Spanned {
+            item: FlatShape::Garbage,
+            span: Span::new(0, 1),
+        };
+        let styled = palette.styles_for_shape(&test_shape);
+        assert_eq!(styled[0].item, Color::White.normal().on(Color::Red));
+    }
+
+    #[test]
+    fn themed_palette_parses_explicit_background() {
+        let json = full_theme_json(&[(
+            "external_command",
+            serde_json::json!({"fg": "00ff00", "bg": "000000"}),
+        )]);
+        let mut json_reader = Cursor::new(json);
+        let themed_palette = ThemedPalette::new(&mut json_reader).unwrap();
+        let test_shape = Spanned {
+            item: FlatShape::ExternalCommand,
+            span: Span::new(0, 1),
+        };
+        let styled = themed_palette.styles_for_shape(&test_shape);
+        assert_eq!(
+            styled[0].item,
+            Color::Rgb(0, 255, 0).normal().on(Color::Rgb(0, 0, 0))
+        );
+    }
+
+    #[test]
+    fn theme_variants_select_light_or_dark() {
+        let light: serde_json::Value =
+            serde_json::from_str(&full_theme_json(&[("decimal", serde_json::json!("000000"))]))
+                .unwrap();
+        let dark: serde_json::Value =
+            serde_json::from_str(&full_theme_json(&[("decimal", serde_json::json!("ffffff"))]))
+                .unwrap();
+        let json = serde_json::json!({ "light": light, "dark": dark }).to_string();
+
+        let mut json_reader = Cursor::new(json);
+        let palette = ThemedPalette::new(&mut json_reader).unwrap();
+        let test_shape = Spanned {
+            item: FlatShape::Decimal,
+            span: Span::new(0, 1),
+        };
+
+        let light_palette = palette.for_background(false);
+        let dark_palette = palette.for_background(true);
+        assert_eq!(
+            light_palette.styles_for_shape(&test_shape)[0].item,
+            Color::Rgb(0, 0, 0).normal(),
+        );
+        assert_eq!(
+            dark_palette.styles_for_shape(&test_shape)[0].item,
+            Color::Rgb(255, 255, 255).normal(),
+        );
+    }
 }